@@ -27,6 +27,26 @@ pub fn compute_centrality_to_csv(graph: &TransitGraph, output_path: &str) -> Res
     Ok(())
 }
 
+/// Compute betweenness centrality for all stops in the graph
+/// and write results to a CSV file.
+/// Inputs:
+/// - graph: reference to TransitGraph
+/// - output_path: path to output CSV
+/// Output: Result<(), std::io::Error>
+pub fn compute_betweenness_to_csv(graph: &TransitGraph, output_path: &str) -> Result<(), std::io::Error> {
+    let betweenness = graph.compute_betweenness_centrality();
+    let file = File::create(output_path)?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "stop_id,betweenness")?;
+    // Write each stop_id and its centrality score to file
+    for (stop_id, score) in betweenness {
+        writeln!(writer, "{},{}", stop_id, score)?;
+    }
+
+    Ok(())
+}
+
 /// Assign each census tract to the closest transit stop,
 /// and write the assignments to a CSV file.
 /// Inputs:
@@ -35,10 +55,12 @@ pub fn compute_centrality_to_csv(graph: &TransitGraph, output_path: &str) -> Res
 /// - output_path: path to write results
 /// Output: Result<(), std::io::Error>
 pub fn cluster_neighborhoods_to_csv(
-    graph: &TransitGraph,
+    graph: &mut TransitGraph,
     census: &Vec<(String, String, f64, f64)>,
     output_path: &str,
 ) -> Result<(), std::io::Error> {
+    graph.build_spatial_index();
+
     let file = File::create(output_path)?;
     let mut writer = BufWriter::new(file);
 
@@ -49,8 +71,8 @@ pub fn cluster_neighborhoods_to_csv(
             println!("Processing tract {} of {}", i + 1, census.len());
         }
 
-        // Find the stop closest to this tract's lat/lon
-        if let Some((closest_stop, _)) = graph.find_closest_stop(*lat, *lon) {
+        // Find the stop closest to this tract's lat/lon via the spatial index
+        if let Some((closest_stop, _)) = graph.nearest_stop(*lat, *lon) {
             writeln!(writer, "{}\t{}\t{}", tract_id, tract_name, closest_stop)?;
         }
     }
@@ -2,14 +2,88 @@
 //! Builds and manages the transit graph using stops as nodes and trips as edges.
 //! Also provides utilities for calculating distances and closest nodes.
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
 use crate::parser::{Stop, GTFSData};
+use rayon::prelude::*;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use serde::{Deserialize, Serialize};
+
+/// Wraps an `f64` so it can be used as a `BinaryHeap` key. `f64` isn't `Ord`
+/// because of `NaN`; travel times are never `NaN` in practice, so comparison
+/// falls back to `partial_cmp` and treats the impossible `NaN` case as equal
+/// rather than panicking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NonNan(f64);
+
+impl Eq for NonNan {}
+
+impl PartialOrd for NonNan {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NonNan {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so `BinaryHeap` (a max-heap) pops the smallest distance first.
+        other.0.partial_cmp(&self.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A stop's coordinates, indexed by the R-tree. Kept separate from `Stop`
+/// so the tree doesn't need to borrow from (or duplicate all of) `nodes`.
+///
+/// `proj_lon` is an equirectangular projection of `lon` (scaled by
+/// `cos(ref_lat)`, see `build_spatial_index`), not the raw degrees value.
+/// Ranking by raw (lon, lat) would weight east-west separation the same as
+/// north-south at every latitude, which is wrong off the equator and would
+/// silently reintroduce the Euclidean bias haversine_distance exists to fix.
+/// Projecting lon first makes R-tree nearest-neighbor order agree with
+/// great-circle order.
+struct StopPoint {
+    stop_id: String,
+    lat: f64,
+    lon: f64,
+    proj_lon: f64,
+}
+
+impl RTreeObject for StopPoint {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point([self.proj_lon, self.lat])
+    }
+}
+
+impl PointDistance for StopPoint {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let dlon = self.proj_lon - point[0];
+        let dlat = self.lat - point[1];
+        dlon * dlon + dlat * dlat
+    }
+}
+
+/// On-disk representation of a `TransitGraph`: just `nodes` and `edges`. The
+/// spatial index is excluded since it's a derived, in-memory-only structure;
+/// `build_spatial_index` rebuilds it cheaply after a load.
+#[derive(Serialize, Deserialize)]
+struct GraphSnapshot {
+    nodes: HashMap<String, Stop>,
+    edges: HashMap<String, Vec<(String, f64)>>,
+}
 
 /// Represents a graph of transit stops and their connections.
 /// Used for centrality analysis and tract clustering.
 pub struct TransitGraph {
-    pub nodes: HashMap<String, Stop>,       // stop_id → Stop
-    pub edges: HashMap<String, Vec<String>>, // stop_id → list of connected stop_ids
+    pub nodes: HashMap<String, Stop>, // stop_id → Stop
+    pub edges: HashMap<String, Vec<(String, f64)>>, // stop_id → list of (connected stop_id, travel time in seconds)
+    spatial_index: Option<RTree<StopPoint>>,
+    /// Reference latitude (radians) the spatial index's longitudes are projected
+    /// against. Must be reused at query time so projected coordinates line up.
+    spatial_ref_lat_rad: f64,
 }
 
 impl TransitGraph {
@@ -18,9 +92,56 @@ impl TransitGraph {
         Self {
             nodes: HashMap::new(),
             edges: HashMap::new(),
+            spatial_index: None,
+            spatial_ref_lat_rad: 0.0,
         }
     }
 
+    /// Build an R-tree over all stops' positions, so `nearest_stop` lookups don't
+    /// need a linear scan over every stop. Longitude is projected by `cos(ref_lat)`
+    /// (an equirectangular projection around the dataset's mean latitude) before
+    /// indexing, so R-tree nearest-neighbor order matches great-circle order
+    /// instead of over-weighting east-west separation.
+    /// Must be called (once) before `nearest_stop` is used; rebuild it
+    /// if `nodes` changes.
+    pub fn build_spatial_index(&mut self) {
+        if self.nodes.is_empty() {
+            self.spatial_index = Some(RTree::new());
+            self.spatial_ref_lat_rad = 0.0;
+            return;
+        }
+
+        let avg_lat: f64 =
+            self.nodes.values().map(|stop| stop.lat).sum::<f64>() / self.nodes.len() as f64;
+        let ref_lat_rad = avg_lat.to_radians();
+
+        let points = self
+            .nodes
+            .values()
+            .map(|stop| StopPoint {
+                stop_id: stop.stop_id.clone(),
+                lat: stop.lat,
+                lon: stop.lon,
+                proj_lon: stop.lon * ref_lat_rad.cos(),
+            })
+            .collect();
+
+        self.spatial_index = Some(RTree::bulk_load(points));
+        self.spatial_ref_lat_rad = ref_lat_rad;
+    }
+
+    /// Find the stop closest to a given latitude/longitude using the spatial index.
+    /// Falls back to `None` if `build_spatial_index` hasn't been called yet.
+    /// Inputs: lat, lon
+    /// Output: Option<(stop_id, distance_meters)>
+    pub fn nearest_stop(&self, lat: f64, lon: f64) -> Option<(String, f64)> {
+        let tree = self.spatial_index.as_ref()?;
+        let proj_lon = lon * self.spatial_ref_lat_rad.cos();
+        let nearest = tree.nearest_neighbor(&[proj_lon, lat])?;
+        let dist = Self::haversine_distance(lat, lon, nearest.lat, nearest.lon);
+        Some((nearest.stop_id.clone(), dist))
+    }
+
     /// Build graph structure from GTFS stops and connections
     /// Inputs: GTFSData with stops and directional connections
     /// Populates nodes and edges fields
@@ -31,55 +152,237 @@ impl TransitGraph {
             self.edges
                 .entry(conn.from_stop_id.clone())
                 .or_insert_with(Vec::new)
-                .push(conn.to_stop_id.clone());
+                .push((conn.to_stop_id.clone(), conn.travel_time_secs));
         }
     }
 
-    /// Compute closeness centrality for each node using BFS
+    /// Serialize `nodes` and `edges` to a binary cache file with bincode, so a
+    /// later run can skip re-parsing the GTFS feed. The spatial index is not
+    /// persisted; call `build_spatial_index` again after `load`.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let snapshot = GraphSnapshot {
+            nodes: self.nodes.clone(),
+            edges: self.edges.clone(),
+        };
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        bincode::serialize_into(writer, &snapshot)?;
+        Ok(())
+    }
+
+    /// Load `nodes` and `edges` previously written by `save`.
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let snapshot: GraphSnapshot = bincode::deserialize_from(reader)?;
+        Ok(Self {
+            nodes: snapshot.nodes,
+            edges: snapshot.edges,
+            spatial_index: None,
+            spatial_ref_lat_rad: 0.0,
+        })
+    }
+
+    /// Compute closeness centrality for each node using BFS. Every node's BFS is
+    /// independent of every other's, so the per-node scores are computed in
+    /// parallel with rayon (there is no serial fallback path; the public
+    /// signature is unchanged, but the implementation is rayon-only), each
+    /// worker keeping its own local BFS state, then collected.
+    /// Every stop gets an entry (0.0 if disconnected from the rest of the graph),
+    /// matching `compute_betweenness_centrality` so the two metrics cover the same
+    /// stop set.
     /// Returns: HashMap of stop_id to centrality score
     pub fn compute_closeness_centrality(&self) -> HashMap<String, f64> {
-        let mut centrality = HashMap::new();
-
-        for node in self.nodes.keys() {
-            let mut visited = HashSet::new();
-            let mut queue = VecDeque::new();
-            let mut distance_sum = 0.0;
-
-            visited.insert(node.clone());
-            queue.push_back((node.clone(), 0));
-
-            // Breadth-first search to accumulate distances
-            while let Some((current, dist)) = queue.pop_front() {
-                distance_sum += dist as f64;
-
-                if let Some(neighbors) = self.edges.get(&current) {
-                    for neighbor in neighbors {
-                        if !visited.contains(neighbor) {
-                            visited.insert(neighbor.clone());
-                            queue.push_back((neighbor.clone(), dist + 1));
-                        }
+        let nodes: Vec<&String> = self.nodes.keys().collect();
+
+        nodes
+            .par_iter()
+            .map(|node| ((*node).clone(), self.closeness_score(node).unwrap_or(0.0)))
+            .collect()
+    }
+
+    /// BFS from a single node, returning its closeness score (`None` if disconnected
+    /// from every other node). Pulled out of `compute_closeness_centrality` so each
+    /// rayon worker can run it with no state shared across nodes.
+    fn closeness_score(&self, node: &str) -> Option<f64> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut distance_sum = 0.0;
+
+        visited.insert(node.to_string());
+        queue.push_back((node.to_string(), 0));
+
+        // Breadth-first search to accumulate distances
+        while let Some((current, dist)) = queue.pop_front() {
+            distance_sum += dist as f64;
+
+            if let Some(neighbors) = self.edges.get(&current) {
+                for (neighbor, _travel_time) in neighbors {
+                    if !visited.contains(neighbor) {
+                        visited.insert(neighbor.clone());
+                        queue.push_back((neighbor.clone(), dist + 1));
                     }
                 }
             }
+        }
 
-            // Avoid divide-by-zero if disconnected
-            if distance_sum > 0.0 {
-                let score = (visited.len() as f64 - 1.0) / distance_sum;
-                centrality.insert(node.clone(), score);
+        // Avoid divide-by-zero if disconnected
+        if distance_sum > 0.0 {
+            Some((visited.len() as f64 - 1.0) / distance_sum)
+        } else {
+            None
+        }
+    }
+
+    /// Compute betweenness centrality for each node using Brandes' algorithm:
+    /// for every source, a BFS tracks shortest-path counts `sigma` and predecessors,
+    /// then a backward pass over the BFS order accumulates each node's dependency
+    /// on lying along other nodes' shortest paths. Each source's pass is independent,
+    /// so they run in parallel via rayon and the per-source dependency contributions
+    /// are summed into the final scores afterward.
+    /// Returns: HashMap of stop_id to centrality score
+    pub fn compute_betweenness_centrality(&self) -> HashMap<String, f64> {
+        let sources: Vec<&String> = self.nodes.keys().collect();
+
+        let partials: Vec<HashMap<String, f64>> = sources
+            .par_iter()
+            .map(|source| self.betweenness_contribution(source))
+            .collect();
+
+        let mut centrality: HashMap<String, f64> =
+            self.nodes.keys().map(|id| (id.clone(), 0.0)).collect();
+        for partial in partials {
+            for (node, delta) in partial {
+                *centrality.entry(node).or_insert(0.0) += delta;
             }
         }
 
         centrality
     }
 
-    /// Find the stop closest to a given latitude/longitude using Euclidean distance
+    /// Brandes' single-source pass: BFS from `source` tracking shortest-path counts
+    /// and predecessors, then a backward accumulation of dependencies. Returns each
+    /// node's contribution to betweenness centrality from this source alone, so
+    /// `compute_betweenness_centrality` can run one of these per rayon worker with
+    /// no shared mutable state and sum the results afterward.
+    fn betweenness_contribution(&self, source: &str) -> HashMap<String, f64> {
+        let mut predecessors: HashMap<String, Vec<String>> = HashMap::new();
+        let mut sigma: HashMap<String, f64> = HashMap::new();
+        let mut distance: HashMap<String, i64> = HashMap::new();
+        let mut stack = Vec::new();
+        let mut queue = VecDeque::new();
+
+        sigma.insert(source.to_string(), 1.0);
+        distance.insert(source.to_string(), 0);
+        queue.push_back(source.to_string());
+
+        // BFS recording shortest-path counts and the visitation order
+        while let Some(v) = queue.pop_front() {
+            stack.push(v.clone());
+            let dist_v = distance[&v];
+
+            // `edges` can hold parallel duplicate entries for the same (v, w) pair
+            // (one per trip that traverses the stop pair); dedupe here so each
+            // neighbor is only counted once per BFS step, regardless of how many
+            // trips connect v and w.
+            if let Some(neighbors) = self.edges.get(&v) {
+                let unique_neighbors: HashSet<&String> = neighbors.iter().map(|(w, _)| w).collect();
+                for w in unique_neighbors {
+                    // First time we've reached w: record its distance and queue it
+                    if !distance.contains_key(w) {
+                        distance.insert(w.clone(), dist_v + 1);
+                        queue.push_back(w.clone());
+                    }
+                    // w reached via a shortest path through v
+                    if distance[w] == dist_v + 1 {
+                        *sigma.entry(w.clone()).or_insert(0.0) += sigma[&v];
+                        predecessors.entry(w.clone()).or_insert_with(Vec::new).push(v.clone());
+                    }
+                }
+            }
+        }
+
+        // Accumulate dependencies in reverse BFS order
+        let mut delta: HashMap<String, f64> = HashMap::new();
+        while let Some(w) = stack.pop() {
+            if let Some(preds) = predecessors.get(&w) {
+                for v in preds {
+                    let contribution =
+                        (sigma[v] / sigma[&w]) * (1.0 + delta.get(&w).copied().unwrap_or(0.0));
+                    *delta.entry(v.clone()).or_insert(0.0) += contribution;
+                }
+            }
+            if w == source {
+                delta.remove(&w);
+            }
+        }
+
+        delta
+    }
+
+    /// Find the fastest route between two stops using Dijkstra's algorithm over the
+    /// trip-derived travel-time edge weights.
+    /// Inputs: from/to stop IDs
+    /// Output: `Some((path, total_seconds))` ordered from `from` to `to`, or `None` if
+    /// either stop is unknown or no path connects them.
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<(Vec<String>, f64)> {
+        if !self.nodes.contains_key(from) || !self.nodes.contains_key(to) {
+            return None;
+        }
+
+        let mut best_dist: HashMap<String, f64> = HashMap::new();
+        let mut predecessor: HashMap<String, String> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        best_dist.insert(from.to_string(), 0.0);
+        heap.push((NonNan(0.0), from.to_string()));
+
+        while let Some((NonNan(dist), current)) = heap.pop() {
+            if current == to {
+                break;
+            }
+
+            // A stale heap entry from before a shorter path was found.
+            if dist > *best_dist.get(&current).unwrap_or(&f64::INFINITY) {
+                continue;
+            }
+
+            if let Some(neighbors) = self.edges.get(&current) {
+                for (neighbor, weight) in neighbors {
+                    let candidate_dist = dist + weight;
+                    if candidate_dist < *best_dist.get(neighbor).unwrap_or(&f64::INFINITY) {
+                        best_dist.insert(neighbor.clone(), candidate_dist);
+                        predecessor.insert(neighbor.clone(), current.clone());
+                        heap.push((NonNan(candidate_dist), neighbor.clone()));
+                    }
+                }
+            }
+        }
+
+        let total_time = *best_dist.get(to)?;
+
+        // Walk predecessors back from `to` to `from` to recover the ordered path.
+        let mut path = vec![to.to_string()];
+        let mut current = to.to_string();
+        while current != from {
+            current = predecessor.get(&current)?.clone();
+            path.push(current.clone());
+        }
+        path.reverse();
+
+        Some((path, total_time))
+    }
+
+    /// Find the stop closest to a given latitude/longitude with a linear scan over every
+    /// stop, using great-circle distance. O(n) per call; kept as a fallback for callers
+    /// without a spatial index. Prefer `nearest_stop` when one has been built.
     /// Inputs: lat, lon
-    /// Output: Option<(stop_id, distance)>
+    /// Output: Option<(stop_id, distance_meters)>
     pub fn find_closest_stop(&self, lat: f64, lon: f64) -> Option<(String, f64)> {
         let mut closest: Option<(String, f64)> = None;
 
         for (id, stop) in &self.nodes {
-            let dist = Self::euclidean_distance(lat, lon, stop.lat, stop.lon);
+            let dist = Self::haversine_distance(lat, lon, stop.lat, stop.lon);
             match &closest {
                 Some((_, best_dist)) if dist < *best_dist => {
                     closest = Some((id.clone(), dist));
@@ -94,10 +397,94 @@ impl TransitGraph {
         closest
     }
 
-    /// Compute straight-line (Euclidean) distance between two points
-    fn euclidean_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-        let dlat = lat1 - lat2;
-        let dlon = lon1 - lon2;
-        (dlat.powi(2) + dlon.powi(2)).sqrt()
+    /// Compute the great-circle (Haversine) distance between two lat/lon points, in meters.
+    /// Treating lat/lon as flat Cartesian coordinates (Euclidean distance) is wrong even at
+    /// city scale: at Boston's latitude a degree of longitude is noticeably shorter than a
+    /// degree of latitude, which biases nearest-stop matching. Haversine accounts for that.
+    pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+        let lat1_rad = lat1.to_radians();
+        let lat2_rad = lat2.to_radians();
+        let dlat = (lat2 - lat1).to_radians();
+        let dlon = (lon2 - lon1).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2)
+            + lat1_rad.cos() * lat2_rad.cos() * (dlon / 2.0).sin().powi(2);
+        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+        EARTH_RADIUS_METERS * c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: &str, lat: f64, lon: f64) -> Stop {
+        Stop {
+            stop_id: id.to_string(),
+            name: id.to_string(),
+            lat,
+            lon,
+        }
+    }
+
+    #[test]
+    fn haversine_distance_same_point_is_zero() {
+        assert_eq!(TransitGraph::haversine_distance(42.3601, -71.0589, 42.3601, -71.0589), 0.0);
+    }
+
+    #[test]
+    fn haversine_distance_matches_known_one_degree_latitude_separation() {
+        // One degree of latitude is ~111.2km everywhere, regardless of longitude.
+        let dist = TransitGraph::haversine_distance(42.0, -71.0, 43.0, -71.0);
+        assert!((dist - 111_195.0).abs() < 500.0, "unexpected distance: {dist}");
+    }
+
+    #[test]
+    fn shortest_path_prefers_lower_weight_over_fewer_hops() {
+        let mut graph = TransitGraph::new();
+        graph.nodes.insert("A".to_string(), stop("A", 0.0, 0.0));
+        graph.nodes.insert("B".to_string(), stop("B", 0.0, 0.0));
+        graph.nodes.insert("C".to_string(), stop("C", 0.0, 0.0));
+
+        // Direct A->B is expensive; routing through C is cheaper despite the extra hop.
+        graph.edges.insert("A".to_string(), vec![("B".to_string(), 10.0), ("C".to_string(), 1.0)]);
+        graph.edges.insert("C".to_string(), vec![("B".to_string(), 1.0)]);
+
+        let (path, total_time) = graph.shortest_path("A", "B").expect("path should exist");
+        assert_eq!(path, vec!["A".to_string(), "C".to_string(), "B".to_string()]);
+        assert_eq!(total_time, 2.0);
+    }
+
+    #[test]
+    fn shortest_path_returns_none_when_unreachable() {
+        let mut graph = TransitGraph::new();
+        graph.nodes.insert("A".to_string(), stop("A", 0.0, 0.0));
+        graph.nodes.insert("B".to_string(), stop("B", 0.0, 0.0));
+
+        assert!(graph.shortest_path("A", "B").is_none());
+    }
+
+    #[test]
+    fn betweenness_contribution_dedupes_parallel_edges() {
+        // Diamond graph A -> {B, C} -> D, with A->B duplicated as if two trips
+        // traversed the same stop pair. Without deduping, sigma[B] (and every
+        // downstream count derived from it) would double.
+        let mut graph = TransitGraph::new();
+        graph.edges.insert(
+            "A".to_string(),
+            vec![("B".to_string(), 1.0), ("B".to_string(), 1.0), ("C".to_string(), 1.0)],
+        );
+        graph.edges.insert("B".to_string(), vec![("D".to_string(), 1.0)]);
+        graph.edges.insert("C".to_string(), vec![("D".to_string(), 1.0)]);
+
+        let delta = graph.betweenness_contribution("A");
+
+        let b = delta.get("B").copied().unwrap_or(0.0);
+        let c = delta.get("C").copied().unwrap_or(0.0);
+        assert!((b - 0.5).abs() < 1e-9, "expected B's contribution to be 0.5, got {b}");
+        assert!((c - 0.5).abs() < 1e-9, "expected C's contribution to be 0.5, got {c}");
     }
 }
\ No newline at end of file
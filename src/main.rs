@@ -4,41 +4,70 @@
 
 use ds210_finalproj::parser::{load_gtfs_data, load_census_csv};
 use ds210_finalproj::graph::TransitGraph;
-use ds210_finalproj::analysis::{compute_centrality_to_csv, cluster_neighborhoods_to_csv};
+use ds210_finalproj::analysis::{compute_centrality_to_csv, compute_betweenness_to_csv, cluster_neighborhoods_to_csv};
 use std::fs::{create_dir_all, File};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 
+const GRAPH_CACHE_PATH: &str = "output/graph_cache.bin";
+
 fn main() {
     println!("Loading datasets...");
 
-    // Load GTFS transit data and census tract data from file system
-    let gtfs = load_gtfs_data("data/gtfs").expect("Failed to load GTFS");
+    // Ensure output folder exists (also holds the graph cache)
+    create_dir_all("output").expect("Failed to create output directory");
+
     let census = load_census_csv("data/Census_Tracts_2010.csv").expect("Failed to load census CSV");
 
-    println!("Constructing transit graph...");
-    // Build graph from GTFS stop connections
-    let mut graph = TransitGraph::new();
-    graph.build_from_gtfs(&gtfs);
+    let mut graph = if graph_cache_is_fresh("data/gtfs", GRAPH_CACHE_PATH) {
+        println!("Loading cached transit graph from {}...", GRAPH_CACHE_PATH);
+        TransitGraph::load(GRAPH_CACHE_PATH).expect("Failed to load graph cache")
+    } else {
+        println!("Constructing transit graph...");
+        let gtfs = load_gtfs_data("data/gtfs").expect("Failed to load GTFS");
+        let mut graph = TransitGraph::new();
+        graph.build_from_gtfs(&gtfs);
+        graph.save(GRAPH_CACHE_PATH).expect("Failed to write graph cache");
+        graph
+    };
 
     println!("Saving results to output/...");
-    // Ensure output folder exists
-    create_dir_all("output").expect("Failed to create output directory");
 
     // Compute and save centrality scores to CSV
     compute_centrality_to_csv(&graph, "output/centrality.csv").expect("Failed to write centrality.csv");
 
+    // Compute and save betweenness centrality scores to CSV
+    compute_betweenness_to_csv(&graph, "output/betweenness.csv").expect("Failed to write betweenness.csv");
+
     // Cluster census tracts to nearest stop and write results
-    cluster_neighborhoods_to_csv(&graph, &census, "output/tract_clusters.csv").expect("Failed to write tract_clusters.csv");
+    cluster_neighborhoods_to_csv(&mut graph, &census, "output/tract_clusters.csv").expect("Failed to write tract_clusters.csv");
 
     println!("Done. Files written to output/ directory.");
 
     // Print result summaries
     print_top_5_central_stops_with_names(&graph, "output/centrality.csv");
+    print_top_5_transfer_hubs(&graph, "output/betweenness.csv");
     print_tract_counts("output/tract_clusters.csv");
     print_tract_opportunity_summary("output/tract_opportunity.csv");
 }
 
+/// Check whether a cached graph at `cache_path` exists and is newer than every
+/// GTFS source file in `gtfs_dir`, so it's safe to load instead of re-parsing.
+fn graph_cache_is_fresh(gtfs_dir: &str, cache_path: &str) -> bool {
+    let cache_modified = match std::fs::metadata(cache_path).and_then(|m| m.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return false,
+    };
+
+    ["stops.txt", "trips.txt", "stop_times.txt"]
+        .iter()
+        .all(|file| {
+            let source_modified = std::fs::metadata(format!("{}/{}", gtfs_dir, file))
+                .and_then(|m| m.modified());
+            matches!(source_modified, Ok(modified) if modified <= cache_modified)
+        })
+}
+
 /// Print top 5 stops with highest closeness centrality
 /// Inputs: reference to graph and path to centrality CSV
 /// Output: printed ranked stop info with name and score
@@ -74,6 +103,41 @@ fn print_top_5_central_stops_with_names(graph: &TransitGraph, path: &str) {
     }
 }
 
+/// Print top 5 stops with highest betweenness centrality (transfer hubs)
+/// Inputs: reference to graph and path to betweenness CSV
+/// Output: printed ranked stop info with name and score
+fn print_top_5_transfer_hubs(graph: &TransitGraph, path: &str) {
+    println!("\nTop 5 Transfer Hubs (highest betweenness):");
+
+    // Load betweenness CSV
+    let data = std::fs::read_to_string(path).expect("Failed to read betweenness.csv");
+
+    // Parse each line into (stop_id, score)
+    let mut rows: Vec<(String, f64)> = data
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() == 2 {
+                let stop_id = parts[0].to_string();
+                let betweenness = parts[1].parse::<f64>().ok()?;
+                Some((stop_id, betweenness))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // Sort descending by betweenness score
+    rows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    // Print top 5
+    for (i, (stop_id, score)) in rows.iter().take(5).enumerate() {
+        let name = graph.nodes.get(stop_id).map(|s| s.name.as_str()).unwrap_or("Unknown");
+        println!("{}. {} (ID: {}) → Betweenness: {:.4}", i + 1, name, stop_id, score);
+    }
+}
+
 /// Count how many census tracts were assigned to each stop
 /// Inputs: path to tract_clusters.csv
 /// Output: prints top 5 stops by number of tracts
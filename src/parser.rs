@@ -2,12 +2,15 @@
 //! Responsible for loading and parsing GTFS transit data and census tract geometry from CSV files.
 
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use serde::{Deserialize, Serialize};
+
 /// Represents a transit stop, including its ID, name, and coordinates.
 /// Used as a node in the TransitGraph.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Stop {
     pub stop_id: String,
     pub name: String,
@@ -27,6 +30,9 @@ pub struct Route {
 pub struct Connection {
     pub from_stop_id: String,
     pub to_stop_id: String,
+    /// Estimated travel time between the two stops, in seconds, derived from the
+    /// `stop_times.txt` departure/arrival times of the trip this connection came from.
+    pub travel_time_secs: f64,
 }
 
 /// Represents a GTFS dataset: stops and connections between them.
@@ -36,74 +42,166 @@ pub struct GTFSData {
     pub connections: Vec<Connection>,
 }
 
+/// Error produced while loading a GTFS feed, distinguishing a missing
+/// file from a row that failed to parse against the expected columns.
+#[derive(Debug)]
+pub enum GtfsError {
+    /// One of the required GTFS files (`stops.txt`, `trips.txt`, `stop_times.txt`) is missing.
+    MissingFile { file: String, source: std::io::Error },
+    /// A row was present but malformed, or a required column was absent from the header.
+    MalformedRow { file: String, source: csv::Error },
+}
+
+impl fmt::Display for GtfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GtfsError::MissingFile { file, source } => {
+                write!(f, "missing GTFS file '{}': {}", file, source)
+            }
+            GtfsError::MalformedRow { file, source } => {
+                write!(f, "malformed row in '{}': {}", file, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GtfsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GtfsError::MissingFile { source, .. } => Some(source),
+            GtfsError::MalformedRow { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Row shape of `stops.txt`, resolved by header name so column order
+/// and any extra GTFS columns (zone_id, stop_url, ...) don't matter.
+#[derive(Debug, Deserialize)]
+struct StopRecord {
+    stop_id: String,
+    stop_name: String,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+/// Row shape of `trips.txt`. Not used for anything yet, but deserializing it
+/// validates the file against the header so a malformed trips file is
+/// caught rather than silently ignored.
+#[derive(Debug, Deserialize)]
+struct TripRecord {
+    #[allow(dead_code)]
+    trip_id: String,
+}
+
+/// Row shape of `stop_times.txt`, used to order stops within each trip and
+/// to derive the travel time between consecutive stops.
+#[derive(Debug, Deserialize)]
+struct StopTimeRecord {
+    trip_id: String,
+    arrival_time: String,
+    departure_time: String,
+    stop_id: String,
+    stop_sequence: u32,
+}
+
+/// Parse a GTFS `HH:MM:SS` timestamp into seconds since midnight. GTFS allows
+/// hours past 23 to represent service that runs past midnight, so this is not
+/// a wall-clock time and can't use `chrono::NaiveTime`.
+fn parse_gtfs_time(value: &str) -> Option<i64> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds: i64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Open a GTFS CSV file and return a header-aware reader, or a
+/// `GtfsError::MissingFile` if the file doesn't exist.
+fn open_gtfs_csv(path: &str) -> Result<csv::Reader<File>, GtfsError> {
+    let file = File::open(path).map_err(|source| GtfsError::MissingFile {
+        file: path.to_string(),
+        source,
+    })?;
+    Ok(csv::ReaderBuilder::new().has_headers(true).from_reader(file))
+}
+
+/// Deserialize every row of a GTFS CSV file into `T`, resolving fields by
+/// header name rather than column position.
+fn read_gtfs_records<T: for<'de> Deserialize<'de>>(path: &str) -> Result<Vec<T>, GtfsError> {
+    let mut reader = open_gtfs_csv(path)?;
+    let mut records = Vec::new();
+    for result in reader.deserialize::<T>() {
+        let record = result.map_err(|source| GtfsError::MalformedRow {
+            file: path.to_string(),
+            source,
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
 /// Load GTFS transit data from a directory containing GTFS CSV files.
 /// Inputs: path to directory (string)
 /// Outputs: GTFSData struct (stops + connections)
-pub fn load_gtfs_data(dir: &str) -> Result<GTFSData, std::io::Error> {
+pub fn load_gtfs_data(dir: &str) -> Result<GTFSData, GtfsError> {
     let stops_path = format!("{}/stops.txt", dir);
-    let stop_times_path = format!("{}/stop_times.txt", dir);
     let trips_path = format!("{}/trips.txt", dir);
-
-    let stops_file = File::open(stops_path)?;
-    let stop_times_file = File::open(stop_times_path)?;
-    let trips_file = File::open(trips_path)?;
-
-    let stops_reader = BufReader::new(stops_file);
-    let stop_times_reader = BufReader::new(stop_times_file);
-    let trips_reader = BufReader::new(trips_file);
-
-    let mut stops = HashMap::new();
-    let mut stop_sequence_map: HashMap<String, Vec<(u32, String)>> = HashMap::new();
+    let stop_times_path = format!("{}/stop_times.txt", dir);
 
     // Parse stops.txt → Build map of stop_id to Stop struct
-    for (i, line) in stops_reader.lines().enumerate() {
-        let line = line?;
-        if i == 0 {
-            continue;
-        }
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() < 6 {
-            continue;
-        }
-        let stop = Stop {
-            stop_id: parts[0].to_string(),
-            name: parts[2].to_string(),
-            lat: parts[4].parse().unwrap_or(0.0),
-            lon: parts[5].parse().unwrap_or(0.0),
-        };
-        stops.insert(stop.stop_id.clone(), stop);
+    let stop_records: Vec<StopRecord> = read_gtfs_records(&stops_path)?;
+    let mut stops = HashMap::new();
+    for record in stop_records {
+        stops.insert(
+            record.stop_id.clone(),
+            Stop {
+                stop_id: record.stop_id,
+                name: record.stop_name,
+                lat: record.stop_lat,
+                lon: record.stop_lon,
+            },
+        );
     }
 
-    // Parse stop_times.txt → Map trip_id to list of stop sequences
-    for (i, line) in stop_times_reader.lines().enumerate() {
-        let line = line?;
-        if i == 0 {
-            continue;
-        }
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() < 4 {
-            continue;
-        }
-        let trip_id = parts[0];
-        let stop_id = parts[3];
-        let stop_sequence = parts[4].parse::<u32>().unwrap_or(0);
+    // Parse trips.txt → validate it is well-formed (not otherwise used yet)
+    let _trip_records: Vec<TripRecord> = read_gtfs_records(&trips_path)?;
+
+    // Parse stop_times.txt → Map trip_id to list of stop sequences with their times
+    let stop_time_records: Vec<StopTimeRecord> = read_gtfs_records(&stop_times_path)?;
+    let mut stop_sequence_map: HashMap<String, Vec<(u32, String, Option<i64>, Option<i64>)>> =
+        HashMap::new();
+    for record in stop_time_records {
+        let arrival_secs = parse_gtfs_time(&record.arrival_time);
+        let departure_secs = parse_gtfs_time(&record.departure_time);
         stop_sequence_map
-            .entry(trip_id.to_string())
+            .entry(record.trip_id)
             .or_insert_with(Vec::new)
-            .push((stop_sequence, stop_id.to_string()));
+            .push((record.stop_sequence, record.stop_id, arrival_secs, departure_secs));
     }
 
     let mut connections = Vec::new();
 
     // Build connections by ordering stops in each trip
     for (_trip_id, mut stops_seq) in stop_sequence_map {
-        stops_seq.sort_by_key(|(seq, _)| *seq);
+        stops_seq.sort_by_key(|(seq, _, _, _)| *seq);
         for i in 0..stops_seq.len().saturating_sub(1) {
-            let from = &stops_seq[i].1;
-            let to = &stops_seq[i + 1].1;
+            let (_, from, _, departure_secs) = &stops_seq[i];
+            let (_, to, arrival_secs, _) = &stops_seq[i + 1];
+
+            // Estimated travel time is the gap between departing `from` and arriving at
+            // `to`. Fall back to 0.0 when either timestamp is missing or unparsable
+            // (GTFS allows blank times for interpolated stops).
+            let travel_time_secs = match (departure_secs, arrival_secs) {
+                (Some(departure), Some(arrival)) if *arrival >= *departure => {
+                    (*arrival - *departure) as f64
+                }
+                _ => 0.0,
+            };
+
             connections.push(Connection {
                 from_stop_id: from.clone(),
                 to_stop_id: to.clone(),
+                travel_time_secs,
             });
         }
     }
@@ -137,4 +235,4 @@ pub fn load_census_csv(path: &str) -> Result<Vec<(String, String, f64, f64)>, st
     }
 
     Ok(results)
-}
\ No newline at end of file
+}